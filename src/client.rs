@@ -0,0 +1,604 @@
+use std::collections::{HashMap, VecDeque};
+use std::num::{NonZeroU32, NonZeroUsize};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::stream::{self, StreamExt};
+use governor::{
+    clock::DefaultClock,
+    state::{InMemoryState, NotKeyed},
+    Quota, RateLimiter,
+};
+use lru::LruCache;
+
+use crate::{
+    handle_json_response, handle_text_response, validate_ip, DualStackIp, IPInfo, IpApiError,
+    PublicIpSource, BASE_URL,
+};
+
+/// Default capacity for the optional lookup cache, used when a TTL or cache
+/// size is requested without an explicit capacity.
+const DEFAULT_CACHE_SIZE: usize = 256;
+
+/// Default number of IPs per chunk for [`IpApiClient::query_bulk`].
+const DEFAULT_BULK_CHUNK_SIZE: usize = 100;
+
+/// Default number of chunk requests [`IpApiClient::query_bulk`] keeps in
+/// flight at once.
+const DEFAULT_BULK_CONCURRENCY: usize = 4;
+
+/// A non-keyed, in-process rate limiter shared across every outbound request
+/// an [`IpApiClient`] makes.
+type Limiter = RateLimiter<NotKeyed, InMemoryState, DefaultClock>;
+
+struct CacheEntry {
+    info: IPInfo,
+    inserted_at: Instant,
+}
+
+struct Cache {
+    entries: Mutex<LruCache<String, CacheEntry>>,
+    ttl: Option<Duration>,
+}
+
+impl Cache {
+    fn get(&self, ip: &str) -> Option<IPInfo> {
+        let mut entries = self.entries.lock().unwrap();
+        let entry = entries.get(ip)?;
+        if let Some(ttl) = self.ttl {
+            if entry.inserted_at.elapsed() > ttl {
+                entries.pop(ip);
+                return None;
+            }
+        }
+        Some(entry.info.clone())
+    }
+
+    fn insert(&self, ip: String, info: IPInfo) {
+        self.entries.lock().unwrap().put(
+            ip,
+            CacheEntry {
+                info,
+                inserted_at: Instant::now(),
+            },
+        );
+    }
+
+    fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+/// A reusable client for the ipquery.io API.
+///
+/// An `IpApiClient` owns a single pooled `reqwest::Client` that is reused
+/// across requests. The free functions share one default-configured
+/// instance; build your own with [`IpApiClient::builder`] to set a timeout,
+/// user agent, proxy, cache or rate limit, and share it across your
+/// application.
+#[derive(Debug, Clone)]
+pub struct IpApiClient {
+    http: reqwest::Client,
+    base_url: String,
+    cache: Option<Arc<Cache>>,
+    public_ip_source: PublicIpSource,
+    rate_limiter: Option<Arc<Limiter>>,
+}
+
+impl std::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache").field("ttl", &self.ttl).finish()
+    }
+}
+
+impl IpApiClient {
+    /// Starts building an `IpApiClient` with default settings (no timeout,
+    /// the default `reqwest` user agent, and the public ipquery.io endpoint).
+    pub fn builder() -> IpApiClientBuilder {
+        IpApiClientBuilder::default()
+    }
+
+    /// Waits for a rate-limit permit, if a quota was configured on the
+    /// builder. A no-op otherwise.
+    async fn throttle(&self) {
+        if let Some(limiter) = &self.rate_limiter {
+            limiter.until_ready().await;
+        }
+    }
+
+    /// Fetches the IP information for a given IP address.
+    ///
+    /// Checks the lookup cache first (if one is configured) and only hits
+    /// the network on a miss.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_ip`].
+    pub async fn query_ip(&self, ip: &str) -> Result<IPInfo, IpApiError> {
+        validate_ip(ip)?;
+
+        if let Some(cache) = &self.cache {
+            if let Some(info) = cache.get(ip) {
+                return Ok(info);
+            }
+        }
+
+        let url = format!("{}{}", self.base_url, ip);
+        self.throttle().await;
+        let response = self.http.get(&url).send().await?;
+        let info: IPInfo = handle_json_response(response).await?;
+
+        if let Some(cache) = &self.cache {
+            cache.insert(ip.to_string(), info.clone());
+        }
+
+        Ok(info)
+    }
+
+    /// Fetches information for multiple IP addresses.
+    ///
+    /// Cached hits are served without a network call; the remaining misses
+    /// are split into chunks of 100 IPs and fetched with up to 4 requests in
+    /// flight at once. Use [`query_bulk_chunked`](Self::query_bulk_chunked)
+    /// to control those values explicitly.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_bulk`].
+    pub async fn query_bulk(&self, ips: &[&str]) -> Result<Vec<IPInfo>, IpApiError> {
+        self.query_bulk_chunked(ips, DEFAULT_BULK_CHUNK_SIZE, DEFAULT_BULK_CONCURRENCY)
+            .await
+    }
+
+    /// Fetches information for multiple IP addresses, splitting `ips` into
+    /// chunks of at most `chunk_size` and dispatching up to `concurrency`
+    /// chunk requests at once.
+    ///
+    /// Chunking avoids the URL-length limits a single comma-joined request
+    /// would hit on large lists; bounding concurrency keeps a big sweep from
+    /// flooding the API with unbounded parallel connections. Cached hits are
+    /// served without a network call, and each response entry is matched
+    /// back to its requested IP by the `ip` field it carries (duplicates
+    /// resolved left-to-right) rather than by position, so a chunk that
+    /// drops, reorders, or dedupes an entry doesn't corrupt the rest of the
+    /// results.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IpApiError::InvalidIp`] if any entry in `ips` isn't a
+    /// syntactically valid IP address. See also [`crate::query_bulk`].
+    pub async fn query_bulk_chunked(
+        &self,
+        ips: &[&str],
+        chunk_size: usize,
+        concurrency: usize,
+    ) -> Result<Vec<IPInfo>, IpApiError> {
+        for &ip in ips {
+            validate_ip(ip)?;
+        }
+
+        let chunk_size = chunk_size.max(1);
+        let concurrency = concurrency.max(1);
+
+        let mut results: Vec<Option<IPInfo>> = Vec::with_capacity(ips.len());
+        let mut miss_indices = Vec::new();
+        let mut misses: Vec<&str> = Vec::new();
+        for (i, &ip) in ips.iter().enumerate() {
+            let cached = self.cache.as_ref().and_then(|cache| cache.get(ip));
+            if cached.is_none() {
+                miss_indices.push(i);
+                misses.push(ip);
+            }
+            results.push(cached);
+        }
+
+        if !misses.is_empty() {
+            let chunks: Vec<&[&str]> = misses.chunks(chunk_size).collect();
+            let chunk_count = chunks.len();
+            let mut fetched_chunks: Vec<Option<Vec<IPInfo>>> = vec![None; chunk_count];
+
+            let mut in_flight = stream::iter(chunks.into_iter().enumerate())
+                .map(|(index, chunk)| async move { (index, self.fetch_chunk(chunk).await) })
+                .buffer_unordered(concurrency);
+
+            while let Some((index, result)) = in_flight.next().await {
+                fetched_chunks[index] = Some(result?);
+            }
+
+            let fetched: Vec<IPInfo> = fetched_chunks.into_iter().flatten().flatten().collect();
+            for info in &fetched {
+                if let Some(cache) = &self.cache {
+                    cache.insert(info.ip.clone(), info.clone());
+                }
+            }
+
+            for (i, info) in reassemble(ips, &miss_indices, fetched) {
+                results[i] = Some(info);
+            }
+        }
+
+        Ok(results.into_iter().flatten().collect())
+    }
+
+    /// Fetches a single chunk's worth of IPs as one comma-joined request.
+    async fn fetch_chunk(&self, ips: &[&str]) -> Result<Vec<IPInfo>, IpApiError> {
+        let ip_list = ips.join(",");
+        let url = format!("{}{}", self.base_url, ip_list);
+        self.throttle().await;
+        let response = self.http.get(&url).send().await?;
+        handle_json_response(response).await
+    }
+
+    /// Fetches the public IP address as seen by the API.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_own_ip`].
+    pub async fn query_own_ip(&self) -> Result<String, IpApiError> {
+        self.throttle().await;
+        let response = self.http.get(&self.base_url).send().await?;
+        handle_text_response(response).await
+    }
+
+    /// Like [`query_ip`](Self::query_ip), but against a caller-supplied
+    /// endpoint instead of the client's configured base URL.
+    ///
+    /// Reuses the client's pooled connection and rate limiter, unlike the
+    /// bare free function this backs; it does not consult or populate the
+    /// lookup cache, since cached entries aren't keyed by endpoint.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_ip_with_endpoint`].
+    pub async fn query_ip_at(&self, ip: &str, endpoint: &str) -> Result<IPInfo, IpApiError> {
+        validate_ip(ip)?;
+        let url = format!("{}{}", endpoint, ip);
+        self.throttle().await;
+        let response = self.http.get(&url).send().await?;
+        handle_json_response(response).await
+    }
+
+    /// Like [`query_bulk`](Self::query_bulk), but against a caller-supplied
+    /// endpoint instead of the client's configured base URL.
+    ///
+    /// Reuses the client's pooled connection and rate limiter; it does not
+    /// chunk the request or consult the lookup cache.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_bulk_with_endpoint`].
+    pub async fn query_bulk_at(
+        &self,
+        ips: &[&str],
+        endpoint: &str,
+    ) -> Result<Vec<IPInfo>, IpApiError> {
+        for &ip in ips {
+            validate_ip(ip)?;
+        }
+        let ip_list = ips.join(",");
+        let url = format!("{}{}", endpoint, ip_list);
+        self.throttle().await;
+        let response = self.http.get(&url).send().await?;
+        handle_json_response(response).await
+    }
+
+    /// Like [`query_own_ip`](Self::query_own_ip), but against a
+    /// caller-supplied endpoint instead of the client's configured base URL.
+    ///
+    /// # Errors
+    ///
+    /// See [`crate::query_own_ip_with_endpoint`].
+    pub async fn query_own_ip_at(&self, endpoint: &str) -> Result<String, IpApiError> {
+        self.throttle().await;
+        let response = self.http.get(endpoint).send().await?;
+        handle_text_response(response).await
+    }
+
+    /// Fetches the public IPv4 address, using the configured
+    /// [`PublicIpSource`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IpApiError::UnsupportedPublicIpSource`] if the configured
+    /// source (e.g. [`PublicIpSource::IpQuery`]) can't guarantee an
+    /// IPv4-only response. See also [`crate::query_own_ip`].
+    pub async fn query_own_ipv4(&self) -> Result<String, IpApiError> {
+        let url = self
+            .public_ip_source
+            .ipv4_url()
+            .ok_or(IpApiError::UnsupportedPublicIpSource(self.public_ip_source))?;
+        self.throttle().await;
+        let response = self.http.get(url).send().await?;
+        handle_text_response(response).await
+    }
+
+    /// Fetches the public IPv6 address, using the configured
+    /// [`PublicIpSource`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IpApiError::UnsupportedPublicIpSource`] if the configured
+    /// source (e.g. [`PublicIpSource::IpQuery`]) can't guarantee an
+    /// IPv6-only response. See also [`crate::query_own_ip`].
+    pub async fn query_own_ipv6(&self) -> Result<String, IpApiError> {
+        let url = self
+            .public_ip_source
+            .ipv6_url()
+            .ok_or(IpApiError::UnsupportedPublicIpSource(self.public_ip_source))?;
+        self.throttle().await;
+        let response = self.http.get(url).send().await?;
+        handle_text_response(response).await
+    }
+
+    /// Fetches the public IPv4 and IPv6 addresses concurrently, for reliable
+    /// dual-stack detection.
+    ///
+    /// # Errors
+    ///
+    /// Fails if either lookup fails; see [`crate::query_own_ip`].
+    pub async fn query_own_ip_both(&self) -> Result<DualStackIp, IpApiError> {
+        let (v4, v6) = tokio::try_join!(self.query_own_ipv4(), self.query_own_ipv6())?;
+        Ok(DualStackIp { v4, v6 })
+    }
+
+    /// Removes every entry from the lookup cache. A no-op if no cache was
+    /// configured.
+    pub fn clear_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.clear();
+        }
+    }
+}
+
+/// Matches fetched chunk responses back to the requested IPs they belong to.
+///
+/// `miss_indices` holds the positions in `ips` that were fetched over the
+/// network (as opposed to served from the cache); `fetched` is every
+/// [`IPInfo`] the chunks returned, in whatever order the API sent them.
+/// Returns `(index, info)` pairs suitable for writing straight into a
+/// results vector indexed like `ips`.
+///
+/// Matching is done by the `ip` field rather than by position, since a
+/// chunk response may drop, reorder, or dedupe entries (e.g. a bogon
+/// address). A requested IP that appears more than once in `ips` has its
+/// occurrences resolved left-to-right against the matching responses.
+fn reassemble(
+    ips: &[&str],
+    miss_indices: &[usize],
+    fetched: Vec<IPInfo>,
+) -> Vec<(usize, IPInfo)> {
+    let mut pending: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for &i in miss_indices {
+        pending.entry(ips[i]).or_default().push_back(i);
+    }
+
+    let mut matched = Vec::with_capacity(fetched.len());
+    for info in fetched {
+        if let Some(queue) = pending.get_mut(info.ip.as_str()) {
+            if let Some(i) = queue.pop_front() {
+                matched.push((i, info));
+            }
+        }
+    }
+    matched
+}
+
+/// Builds an [`IpApiClient`] with a custom base URL, timeout, user agent,
+/// proxy, and/or lookup cache.
+#[derive(Debug, Default)]
+pub struct IpApiClientBuilder {
+    base_url: Option<String>,
+    timeout: Option<Duration>,
+    user_agent: Option<String>,
+    proxy: Option<reqwest::Proxy>,
+    cache_size: Option<NonZeroUsize>,
+    cache_ttl: Option<Duration>,
+    public_ip_source: PublicIpSource,
+    rate_limit: Option<NonZeroU32>,
+}
+
+impl IpApiClientBuilder {
+    /// Overrides the base URL the client queries against. Defaults to the
+    /// public ipquery.io API.
+    pub fn base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = Some(base_url.into());
+        self
+    }
+
+    /// Sets the per-request timeout. Unset by default, matching `reqwest`'s
+    /// own default of no timeout.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets the `User-Agent` header sent with every request.
+    pub fn user_agent(mut self, user_agent: impl Into<String>) -> Self {
+        self.user_agent = Some(user_agent.into());
+        self
+    }
+
+    /// Routes every request through the given HTTP/HTTPS proxy.
+    pub fn proxy(mut self, proxy: reqwest::Proxy) -> Self {
+        self.proxy = Some(proxy);
+        self
+    }
+
+    /// Enables the in-memory lookup cache with the given capacity (number of
+    /// IPs remembered). Enabling the cache without a TTL means entries never
+    /// expire on their own and are only evicted once the capacity is
+    /// exceeded.
+    pub fn with_cache_size(mut self, capacity: NonZeroUsize) -> Self {
+        self.cache_size = Some(capacity);
+        self
+    }
+
+    /// Sets how long a cached entry stays valid. Enabling a TTL without an
+    /// explicit [`with_cache_size`](Self::with_cache_size) defaults the
+    /// capacity to 256 entries.
+    pub fn with_cache_ttl(mut self, ttl: Duration) -> Self {
+        self.cache_ttl = Some(ttl);
+        self
+    }
+
+    /// Selects which provider `query_own_ipv4`/`query_own_ipv6`/
+    /// `query_own_ip_both` resolve against. Defaults to
+    /// [`PublicIpSource::Icanhazip`].
+    pub fn public_ip_source(mut self, source: PublicIpSource) -> Self {
+        self.public_ip_source = source;
+        self
+    }
+
+    /// Caps every outbound request the client makes to `rps` requests per
+    /// second, so bulk sweeps stay within the API's limits automatically
+    /// instead of every caller having to hand-roll throttling.
+    pub fn with_rate_limit(mut self, rps: NonZeroU32) -> Self {
+        self.rate_limit = Some(rps);
+        self
+    }
+
+    /// Builds the [`IpApiClient`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`IpApiError::Http`] if the underlying `reqwest::Client`
+    /// could not be constructed (for example, an invalid proxy).
+    pub fn build(self) -> Result<IpApiClient, IpApiError> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = self.timeout {
+            builder = builder.timeout(timeout);
+        }
+        if let Some(user_agent) = self.user_agent {
+            builder = builder.user_agent(user_agent);
+        }
+        if let Some(proxy) = self.proxy {
+            builder = builder.proxy(proxy);
+        }
+        let http = builder.build()?;
+
+        let cache = if self.cache_size.is_some() || self.cache_ttl.is_some() {
+            let capacity = self.cache_size.unwrap_or_else(|| {
+                NonZeroUsize::new(DEFAULT_CACHE_SIZE).expect("default cache size is nonzero")
+            });
+            Some(Arc::new(Cache {
+                entries: Mutex::new(LruCache::new(capacity)),
+                ttl: self.cache_ttl,
+            }))
+        } else {
+            None
+        };
+
+        let rate_limiter = self
+            .rate_limit
+            .map(|rps| Arc::new(RateLimiter::direct(Quota::per_second(rps))));
+
+        Ok(IpApiClient {
+            http,
+            base_url: self.base_url.unwrap_or_else(|| BASE_URL.to_string()),
+            cache,
+            public_ip_source: self.public_ip_source,
+            rate_limiter,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample_info(ip: &str) -> IPInfo {
+        IPInfo {
+            ip: ip.to_string(),
+            isp: None,
+            location: None,
+            risk: None,
+        }
+    }
+
+    #[test]
+    fn cache_returns_a_stored_entry() {
+        let cache = Cache {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(2).unwrap())),
+            ttl: None,
+        };
+        cache.insert("8.8.8.8".to_string(), sample_info("8.8.8.8"));
+        assert_eq!(cache.get("8.8.8.8").map(|i| i.ip), Some("8.8.8.8".to_string()));
+        assert_eq!(cache.get("1.1.1.1"), None);
+    }
+
+    #[test]
+    fn cache_evicts_the_least_recently_used_entry_past_capacity() {
+        let cache = Cache {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(1).unwrap())),
+            ttl: None,
+        };
+        cache.insert("8.8.8.8".to_string(), sample_info("8.8.8.8"));
+        cache.insert("1.1.1.1".to_string(), sample_info("1.1.1.1"));
+        assert_eq!(cache.get("8.8.8.8"), None);
+        assert_eq!(cache.get("1.1.1.1").map(|i| i.ip), Some("1.1.1.1".to_string()));
+    }
+
+    #[test]
+    fn cache_expires_entries_past_their_ttl() {
+        let cache = Cache {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(2).unwrap())),
+            ttl: Some(Duration::from_millis(10)),
+        };
+        cache.insert("8.8.8.8".to_string(), sample_info("8.8.8.8"));
+        std::thread::sleep(Duration::from_millis(30));
+        assert_eq!(cache.get("8.8.8.8"), None);
+    }
+
+    #[test]
+    fn cache_clear_removes_every_entry() {
+        let cache = Cache {
+            entries: Mutex::new(LruCache::new(NonZeroUsize::new(2).unwrap())),
+            ttl: None,
+        };
+        cache.insert("8.8.8.8".to_string(), sample_info("8.8.8.8"));
+        cache.clear();
+        assert_eq!(cache.get("8.8.8.8"), None);
+    }
+
+    #[test]
+    fn reassemble_handles_a_dropped_ip() {
+        let ips = ["8.8.8.8", "1.1.1.1"];
+        let miss_indices = [0, 1];
+        // The server silently omitted 1.1.1.1 (e.g. a bogon address).
+        let fetched = vec![sample_info("8.8.8.8")];
+        let matched = reassemble(&ips, &miss_indices, fetched);
+        assert_eq!(matched, vec![(0, sample_info("8.8.8.8"))]);
+    }
+
+    #[test]
+    fn reassemble_handles_a_reordered_response() {
+        let ips = ["8.8.8.8", "1.1.1.1"];
+        let miss_indices = [0, 1];
+        let fetched = vec![sample_info("1.1.1.1"), sample_info("8.8.8.8")];
+        let matched = reassemble(&ips, &miss_indices, fetched);
+        assert_eq!(
+            matched,
+            vec![(1, sample_info("1.1.1.1")), (0, sample_info("8.8.8.8"))]
+        );
+    }
+
+    #[test]
+    fn reassemble_resolves_a_duplicate_ip_left_to_right() {
+        let ips = ["8.8.8.8", "8.8.8.8", "1.1.1.1"];
+        let miss_indices = [0, 1, 2];
+        let fetched = vec![
+            sample_info("1.1.1.1"),
+            sample_info("8.8.8.8"),
+            sample_info("8.8.8.8"),
+        ];
+        let matched = reassemble(&ips, &miss_indices, fetched);
+        assert_eq!(
+            matched,
+            vec![
+                (2, sample_info("1.1.1.1")),
+                (0, sample_info("8.8.8.8")),
+                (1, sample_info("8.8.8.8")),
+            ]
+        );
+    }
+}
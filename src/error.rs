@@ -0,0 +1,73 @@
+use thiserror::Error;
+
+/// Errors that can occur while querying the ipquery.io API.
+#[derive(Debug, Error)]
+pub enum IpApiError {
+    /// The underlying HTTP request failed (DNS, TLS, connection, timeout, ...).
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+
+    /// The response body could not be decoded into the expected shape.
+    #[error("failed to decode response body: {0}")]
+    Decode(String),
+
+    /// The supplied string is not a valid IP address.
+    #[error("invalid IP address: {0}")]
+    InvalidIp(String),
+
+    /// The API responded with HTTP 429 Too Many Requests.
+    #[error("rate limited by the API")]
+    RateLimited,
+
+    /// The API responded with a non-2xx status that isn't a rate limit.
+    #[error("API returned {status}: {message}")]
+    ApiError { status: u16, message: String },
+
+    /// The configured [`crate::PublicIpSource`] can't guarantee a
+    /// family-pinned IPv4-only/IPv6-only response.
+    #[error("{0:?} does not support family-pinned IPv4/IPv6 lookups")]
+    UnsupportedPublicIpSource(crate::PublicIpSource),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_message_includes_cause() {
+        let err = IpApiError::Decode("missing field `ip`".to_string());
+        assert_eq!(
+            err.to_string(),
+            "failed to decode response body: missing field `ip`"
+        );
+    }
+
+    #[test]
+    fn invalid_ip_message_includes_input() {
+        let err = IpApiError::InvalidIp("not-an-ip".to_string());
+        assert_eq!(err.to_string(), "invalid IP address: not-an-ip");
+    }
+
+    #[test]
+    fn rate_limited_message_is_fixed() {
+        assert_eq!(IpApiError::RateLimited.to_string(), "rate limited by the API");
+    }
+
+    #[test]
+    fn api_error_message_includes_status_and_body() {
+        let err = IpApiError::ApiError {
+            status: 503,
+            message: "service unavailable".to_string(),
+        };
+        assert_eq!(err.to_string(), "API returned 503: service unavailable");
+    }
+
+    #[test]
+    fn unsupported_public_ip_source_message_names_the_source() {
+        let err = IpApiError::UnsupportedPublicIpSource(crate::PublicIpSource::IpQuery);
+        assert_eq!(
+            err.to_string(),
+            "IpQuery does not support family-pinned IPv4/IPv6 lookups"
+        );
+    }
+}
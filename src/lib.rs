@@ -6,6 +6,7 @@
 //! - Query details for a specific IP address
 //! - Bulk query multiple IP addresses
 //! - Fetch your own public IP address
+//! - An [`IpApiClient`] for reusing a single connection pool across many calls
 //!
 //! ## Example Usage
 //!
@@ -21,17 +22,110 @@
 //! }
 //! ```
 //!
+//! The free functions share one default-configured [`IpApiClient`]. To set a
+//! timeout, user agent, proxy, cache or rate limit, build an [`IpApiClient`]
+//! once and reuse it:
+//!
+//! ```rust
+//! use ipapi::IpApiClient;
+//!
+//! #[tokio::main]
+//! async fn main() {
+//!     let client = IpApiClient::builder()
+//!         .timeout(std::time::Duration::from_secs(5))
+//!         .user_agent("my-app/1.0")
+//!         .build()
+//!         .unwrap();
+//!     let ip_info = client.query_ip("8.8.8.8").await.unwrap();
+//!     println!("{:?}", ip_info);
+//! }
+//! ```
+//!
 //! ## License
 //! This project is licensed under the MIT License.
 
-pub use reqwest::Error;
-use serde::{Deserialize, Serialize};
+mod client;
+mod error;
+mod public_ip;
+
+use std::sync::OnceLock;
+
+pub use client::{IpApiClient, IpApiClientBuilder};
+pub use error::IpApiError;
+pub use public_ip::{DualStackIp, PublicIpSource};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
 /// The base URL for the ipquery.io API.
-const BASE_URL: &str = "https://api.ipquery.io/";
+pub(crate) const BASE_URL: &str = "https://api.ipquery.io/";
+
+/// Rejects a string that isn't a syntactically valid IPv4/IPv6 address
+/// before it's ever sent over the network, so a typo produces a clear
+/// [`IpApiError::InvalidIp`] instead of a confusing API/decode error. Only
+/// the syntax is checked; private or reserved (bogon) addresses pass.
+pub(crate) fn validate_ip(ip: &str) -> Result<(), IpApiError> {
+    ip.parse::<std::net::IpAddr>()
+        .map(|_| ())
+        .map_err(|_| IpApiError::InvalidIp(ip.to_string()))
+}
+
+/// Returns the lazily-initialized client shared by the free functions below.
+fn default_client() -> &'static IpApiClient {
+    static DEFAULT: OnceLock<IpApiClient> = OnceLock::new();
+    DEFAULT.get_or_init(|| {
+        IpApiClient::builder()
+            .build()
+            .expect("default IpApiClient should always build")
+    })
+}
+
+/// Turns a raw `reqwest::Response` into either the deserialized payload or an
+/// `IpApiError` describing why it couldn't be used.
+///
+/// Non-2xx responses are inspected before any attempt to parse JSON, so a 429
+/// or an HTML error page produces a meaningful `RateLimited`/`ApiError`
+/// instead of a cryptic decode failure.
+pub(crate) async fn handle_json_response<T: DeserializeOwned>(
+    response: reqwest::Response,
+) -> Result<T, IpApiError> {
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(IpApiError::RateLimited);
+    }
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(IpApiError::ApiError {
+            status: status.as_u16(),
+            message,
+        });
+    }
+    response
+        .json::<T>()
+        .await
+        .map_err(|e| IpApiError::Decode(e.to_string()))
+}
+
+/// Like [`handle_json_response`], but returns the response body as text with
+/// trailing whitespace (such as a final newline) trimmed.
+pub(crate) async fn handle_text_response(
+    response: reqwest::Response,
+) -> Result<String, IpApiError> {
+    let status = response.status();
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(IpApiError::RateLimited);
+    }
+    if !status.is_success() {
+        let message = response.text().await.unwrap_or_default();
+        return Err(IpApiError::ApiError {
+            status: status.as_u16(),
+            message,
+        });
+    }
+    let text = response.text().await?;
+    Ok(text.trim_end().to_string())
+}
 
 /// Represents information about an ISP (Internet Service Provider).
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct ISPInfo {
     /// The Autonomous System Number (ASN) of the ISP.
     pub asn: Option<String>,
@@ -42,7 +136,7 @@ pub struct ISPInfo {
 }
 
 /// Represents information about the geographical location of an IP address.
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct LocationInfo {
     /// The country name.
     pub country: Option<String>,
@@ -64,8 +158,56 @@ pub struct LocationInfo {
     pub localtime: Option<String>,
 }
 
+/// Returns `country_code` if it's a two-letter ASCII alphabetic ISO code,
+/// `None` otherwise (e.g. `"N/A"`, an empty string, or a full country name).
+fn valid_iso_country_code(country_code: &str) -> Option<&str> {
+    if country_code.len() == 2 && country_code.chars().all(|c| c.is_ascii_alphabetic()) {
+        Some(country_code)
+    } else {
+        None
+    }
+}
+
+impl LocationInfo {
+    /// Returns a CDN URL for the country's flag image, built from the
+    /// lowercase ISO `country_code`. Returns `None` if `country_code` is
+    /// missing or isn't a two-letter ISO code.
+    pub fn flag_url(&self) -> Option<String> {
+        let country_code = valid_iso_country_code(self.country_code.as_ref()?)?;
+        Some(format!(
+            "https://flagcdn.com/w320/{}.png",
+            country_code.to_lowercase()
+        ))
+    }
+
+    /// Converts the two-letter ISO `country_code` into its regional-indicator
+    /// Unicode emoji flag (e.g. `"US"` -> `"🇺🇸"`). Returns `None` if
+    /// `country_code` is missing or isn't a two-letter ISO code.
+    pub fn flag_emoji(&self) -> Option<String> {
+        let country_code = valid_iso_country_code(self.country_code.as_ref()?)?;
+        Some(
+            country_code
+                .to_uppercase()
+                .chars()
+                .map(|c| char::from_u32(0x1F1E6 + (c as u32 - 'A' as u32)).unwrap())
+                .collect(),
+        )
+    }
+
+    /// Returns a geo link to the location's `latitude`/`longitude` on Google
+    /// Maps.
+    pub fn maps_url(&self) -> Option<String> {
+        let latitude = self.latitude?;
+        let longitude = self.longitude?;
+        Some(format!(
+            "https://www.google.com/maps?q={},{}",
+            latitude, longitude
+        ))
+    }
+}
+
 /// Represents information about potential risks associated with an IP address.
-#[derive(Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
 pub struct RiskInfo {
     /// Indicates if the IP is associated with a mobile network.
     pub is_mobile: Option<bool>,
@@ -82,7 +224,7 @@ pub struct RiskInfo {
 }
 
 /// Represents the full set of information returned by the API for an IP address.
-#[derive(Debug, Deserialize, Serialize, PartialEq)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
 pub struct IPInfo {
     /// The queried IP address.
     pub ip: String,
@@ -115,11 +257,13 @@ pub struct IPInfo {
 ///
 /// # Errors
 ///
-/// Returns an error if the network request fails or the response cannot be deserialized.
-pub async fn query_ip(ip: &str) -> Result<IPInfo, Error> {
-    let url = format!("{}{}", BASE_URL, ip);
-    let response = reqwest::get(&url).await?.json::<IPInfo>().await?;
-    Ok(response)
+/// Returns [`IpApiError::InvalidIp`] if `ip` isn't a syntactically valid IP
+/// address, [`IpApiError::Http`] if the request fails,
+/// [`IpApiError::Decode`] if the body isn't the expected shape, or
+/// [`IpApiError::RateLimited`] / [`IpApiError::ApiError`] if the API
+/// rejected the request.
+pub async fn query_ip(ip: &str) -> Result<IPInfo, IpApiError> {
+    default_client().query_ip(ip).await
 }
 
 /// Fetches information for multiple IP addresses.
@@ -143,12 +287,30 @@ pub async fn query_ip(ip: &str) -> Result<IPInfo, Error> {
 ///
 /// # Errors
 ///
-/// Returns an error if the network request fails or the response cannot be deserialized.
-pub async fn query_bulk(ips: &[&str]) -> Result<Vec<IPInfo>, Error> {
-    let ip_list = ips.join(",");
-    let url = format!("{}{}", BASE_URL, ip_list);
-    let response = reqwest::get(&url).await?.json::<Vec<IPInfo>>().await?;
-    Ok(response)
+/// Returns [`IpApiError::InvalidIp`] if any entry in `ips` isn't a
+/// syntactically valid IP address, [`IpApiError::Http`] if the request
+/// fails, [`IpApiError::Decode`] if the body isn't the expected shape, or
+/// [`IpApiError::RateLimited`] / [`IpApiError::ApiError`] if the API
+/// rejected the request.
+pub async fn query_bulk(ips: &[&str]) -> Result<Vec<IPInfo>, IpApiError> {
+    default_client().query_bulk(ips).await
+}
+
+/// Fetches information for multiple IP addresses, splitting `ips` into
+/// chunks of at most `chunk_size` and dispatching up to `concurrency` chunk
+/// requests at once.
+///
+/// # Errors
+///
+/// See [`query_bulk`].
+pub async fn query_bulk_chunked(
+    ips: &[&str],
+    chunk_size: usize,
+    concurrency: usize,
+) -> Result<Vec<IPInfo>, IpApiError> {
+    default_client()
+        .query_bulk_chunked(ips, chunk_size, concurrency)
+        .await
 }
 
 /// Fetches the IP address of the current machine.
@@ -168,26 +330,156 @@ pub async fn query_bulk(ips: &[&str]) -> Result<Vec<IPInfo>, Error> {
 ///
 /// # Errors
 ///
-/// Returns an error if the network request fails.
-pub async fn query_own_ip() -> Result<String, Error> {
-    let response = reqwest::get(BASE_URL).await?.text().await?;
-    Ok(response)
+/// Returns [`IpApiError::Http`] if the request fails, or
+/// [`IpApiError::RateLimited`] / [`IpApiError::ApiError`] if the API
+/// rejected the request.
+pub async fn query_own_ip() -> Result<String, IpApiError> {
+    default_client().query_own_ip().await
 }
 
-pub async fn query_ip_with_endpoint(ip: &str, endpoint: &str) -> Result<IPInfo, Error> {
-    let url = format!("{}{}", endpoint, ip);
-    let response = reqwest::get(&url).await?.json::<IPInfo>().await?;
-    Ok(response)
+/// Fetches the public IPv4 address of the current machine.
+///
+/// # Errors
+///
+/// See [`query_own_ip`].
+pub async fn query_own_ipv4() -> Result<String, IpApiError> {
+    default_client().query_own_ipv4().await
 }
 
-pub async fn query_bulk_with_endpoint(ips: &[&str], endpoint: &str) -> Result<Vec<IPInfo>, Error> {
-    let ip_list = ips.join(",");
-    let url = format!("{}{}", endpoint, ip_list);
-    let response = reqwest::get(&url).await?.json::<Vec<IPInfo>>().await?;
-    Ok(response)
+/// Fetches the public IPv6 address of the current machine.
+///
+/// # Errors
+///
+/// See [`query_own_ip`].
+pub async fn query_own_ipv6() -> Result<String, IpApiError> {
+    default_client().query_own_ipv6().await
 }
 
-pub async fn query_own_ip_with_endpoint(endpoint: &str) -> Result<String, Error> {
-    let response = reqwest::get(endpoint).await?.text().await?;
-    Ok(response)
+/// Fetches the public IPv4 and IPv6 addresses of the current machine
+/// concurrently.
+///
+/// # Errors
+///
+/// Fails if either lookup fails; see [`query_own_ip`].
+pub async fn query_own_ip_both() -> Result<DualStackIp, IpApiError> {
+    default_client().query_own_ip_both().await
+}
+
+/// Fetches the IP information for a given IP address from a custom
+/// endpoint, using the shared default client's pooled connection. The
+/// default client has no rate limit and no lookup cache.
+///
+/// # Errors
+///
+/// See [`query_ip`].
+pub async fn query_ip_with_endpoint(ip: &str, endpoint: &str) -> Result<IPInfo, IpApiError> {
+    default_client().query_ip_at(ip, endpoint).await
+}
+
+/// Fetches information for multiple IP addresses from a custom endpoint,
+/// using the shared default client's pooled connection. The default client
+/// has no rate limit.
+///
+/// # Errors
+///
+/// See [`query_bulk`].
+pub async fn query_bulk_with_endpoint(
+    ips: &[&str],
+    endpoint: &str,
+) -> Result<Vec<IPInfo>, IpApiError> {
+    default_client().query_bulk_at(ips, endpoint).await
+}
+
+/// Fetches the public IP address from a custom endpoint, using the shared
+/// default client's pooled connection. The default client has no rate limit.
+///
+/// # Errors
+///
+/// See [`query_own_ip`].
+pub async fn query_own_ip_with_endpoint(endpoint: &str) -> Result<String, IpApiError> {
+    default_client().query_own_ip_at(endpoint).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn location(
+        country_code: Option<&str>,
+        latitude: Option<f64>,
+        longitude: Option<f64>,
+    ) -> LocationInfo {
+        LocationInfo {
+            country: None,
+            country_code: country_code.map(String::from),
+            city: None,
+            state: None,
+            zipcode: None,
+            latitude,
+            longitude,
+            timezone: None,
+            localtime: None,
+        }
+    }
+
+    #[test]
+    fn flag_url_is_none_without_a_country_code() {
+        assert_eq!(location(None, None, None).flag_url(), None);
+    }
+
+    #[test]
+    fn flag_url_lowercases_the_country_code() {
+        assert_eq!(
+            location(Some("US"), None, None).flag_url(),
+            Some("https://flagcdn.com/w320/us.png".to_string())
+        );
+    }
+
+    #[test]
+    fn flag_url_rejects_a_malformed_country_code() {
+        assert_eq!(location(Some("N/A"), None, None).flag_url(), None);
+        assert_eq!(location(Some(""), None, None).flag_url(), None);
+        assert_eq!(location(Some("United States"), None, None).flag_url(), None);
+    }
+
+    #[test]
+    fn flag_emoji_converts_a_valid_code() {
+        assert_eq!(
+            location(Some("us"), None, None).flag_emoji(),
+            Some("🇺🇸".to_string())
+        );
+    }
+
+    #[test]
+    fn flag_emoji_rejects_a_malformed_country_code() {
+        assert_eq!(location(Some("usa"), None, None).flag_emoji(), None);
+        assert_eq!(location(Some(""), None, None).flag_emoji(), None);
+        assert_eq!(location(Some("U1"), None, None).flag_emoji(), None);
+    }
+
+    #[test]
+    fn maps_url_requires_both_coordinates() {
+        assert_eq!(location(None, Some(1.0), None).maps_url(), None);
+        assert_eq!(location(None, None, Some(1.0)).maps_url(), None);
+    }
+
+    #[test]
+    fn maps_url_formats_latitude_and_longitude() {
+        assert_eq!(
+            location(None, Some(37.4224), Some(-122.0842)).maps_url(),
+            Some("https://www.google.com/maps?q=37.4224,-122.0842".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_ip_accepts_ipv4_and_ipv6() {
+        assert!(validate_ip("8.8.8.8").is_ok());
+        assert!(validate_ip("::1").is_ok());
+    }
+
+    #[test]
+    fn validate_ip_rejects_garbage() {
+        let err = validate_ip("not-an-ip").unwrap_err();
+        assert!(matches!(err, IpApiError::InvalidIp(ip) if ip == "not-an-ip"));
+    }
 }
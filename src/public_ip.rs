@@ -0,0 +1,103 @@
+/// A provider of plain-text "what's my IP" endpoints.
+///
+/// Used by [`crate::IpApiClient::query_own_ipv4`] and
+/// [`crate::IpApiClient::query_own_ipv6`] to pick which service to ask, and
+/// configurable via
+/// [`IpApiClientBuilder::public_ip_source`](crate::IpApiClientBuilder::public_ip_source).
+///
+/// Not every provider exposes separate IPv4-only and IPv6-only hosts.
+/// [`PublicIpSource::ipv4_url`]/[`PublicIpSource::ipv6_url`] return `None`
+/// for a source that can't honor the split rather than silently handing
+/// back the same dual-stack URL for both families — see
+/// [`PublicIpSource::IpQuery`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PublicIpSource {
+    /// ipquery.io, the default API this crate is built around.
+    ///
+    /// ipquery.io only exposes a single dual-stack root, so it cannot
+    /// guarantee an IPv4-only or IPv6-only response: whichever address
+    /// family the TCP handshake happens to pick is what you get.
+    /// [`ipv4_url`](Self::ipv4_url) and [`ipv6_url`](Self::ipv6_url) return
+    /// `None` for this source rather than silently returning that same URL
+    /// for both families.
+    IpQuery,
+    /// icanhazip.com's dedicated IPv4-only and IPv6-only hosts. The default
+    /// source.
+    #[default]
+    Icanhazip,
+    /// ipify.org, via its family-pinned `api.ipify.org` (IPv4) and
+    /// `api64.ipify.org` (IPv6) hosts.
+    Ipify64,
+}
+
+impl PublicIpSource {
+    /// The endpoint to use for an IPv4-only lookup, or `None` if this source
+    /// can't guarantee a family-pinned response.
+    pub fn ipv4_url(self) -> Option<&'static str> {
+        match self {
+            PublicIpSource::IpQuery => None,
+            PublicIpSource::Icanhazip => Some("https://ipv4.icanhazip.com"),
+            PublicIpSource::Ipify64 => Some("https://api.ipify.org"),
+        }
+    }
+
+    /// The endpoint to use for an IPv6-only lookup, or `None` if this source
+    /// can't guarantee a family-pinned response.
+    pub fn ipv6_url(self) -> Option<&'static str> {
+        match self {
+            PublicIpSource::IpQuery => None,
+            PublicIpSource::Icanhazip => Some("https://ipv6.icanhazip.com"),
+            PublicIpSource::Ipify64 => Some("https://api64.ipify.org"),
+        }
+    }
+}
+
+/// The result of [`crate::IpApiClient::query_own_ip_both`]: the IPv4 and
+/// IPv6 addresses resolved concurrently from the configured source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DualStackIp {
+    /// The public IPv4 address.
+    pub v4: String,
+    /// The public IPv6 address.
+    pub v6: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_source_can_split_by_family() {
+        assert_eq!(PublicIpSource::default(), PublicIpSource::Icanhazip);
+    }
+
+    #[test]
+    fn ipquery_cannot_split_by_family() {
+        assert_eq!(PublicIpSource::IpQuery.ipv4_url(), None);
+        assert_eq!(PublicIpSource::IpQuery.ipv6_url(), None);
+    }
+
+    #[test]
+    fn icanhazip_urls_are_family_pinned() {
+        assert_eq!(
+            PublicIpSource::Icanhazip.ipv4_url(),
+            Some("https://ipv4.icanhazip.com")
+        );
+        assert_eq!(
+            PublicIpSource::Icanhazip.ipv6_url(),
+            Some("https://ipv6.icanhazip.com")
+        );
+    }
+
+    #[test]
+    fn ipify64_urls_are_family_pinned() {
+        assert_eq!(
+            PublicIpSource::Ipify64.ipv4_url(),
+            Some("https://api.ipify.org")
+        );
+        assert_eq!(
+            PublicIpSource::Ipify64.ipv6_url(),
+            Some("https://api64.ipify.org")
+        );
+    }
+}